@@ -0,0 +1,35 @@
+//! Contains FBX value types used by the writer.
+
+/// A single FBX node property value.
+///
+/// Variants borrow from the caller rather than owning their data, so an emitter can be fed
+/// properties straight out of an existing buffer without an extra copy.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Property<'a> {
+    /// Boolean value (`C` in binary FBX).
+    Bool(bool),
+    /// 16-bit integer value (`Y` in binary FBX).
+    I16(i16),
+    /// 32-bit integer value (`I` in binary FBX).
+    I32(i32),
+    /// 64-bit integer value (`L` in binary FBX).
+    I64(i64),
+    /// Single-precision float value (`F` in binary FBX).
+    F32(f32),
+    /// Double-precision float value (`D` in binary FBX).
+    F64(f64),
+    /// Array of boolean values (`b` in binary FBX).
+    VecBool(&'a [bool]),
+    /// Array of 32-bit integer values (`i` in binary FBX).
+    VecI32(&'a [i32]),
+    /// Array of 64-bit integer values (`l` in binary FBX).
+    VecI64(&'a [i64]),
+    /// Array of single-precision float values (`f` in binary FBX).
+    VecF32(&'a [f32]),
+    /// Array of double-precision float values (`d` in binary FBX).
+    VecF64(&'a [f64]),
+    /// String value (`S` in binary FBX).
+    String(&'a str),
+    /// Raw binary blob (`R` in binary FBX).
+    Binary(&'a [u8]),
+}
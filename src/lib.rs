@@ -0,0 +1,4 @@
+//! Direct, event-based writing of FBX files.
+
+pub mod common;
+pub mod writer;
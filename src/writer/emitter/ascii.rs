@@ -1,10 +1,14 @@
 //! Contains implementation of ASCII FBX emitter.
 use crate::common::Property;
-use crate::writer::error::{Error, Result};
+use crate::writer::emitter::Emitter;
+use crate::writer::error::{Error, ErrorKind, Result};
 use base64;
 use log::{error, warn};
 use std::io::Write;
 
+/// Default `max_line_width`, matching the FBX SDK's ~2048-column wrapping of big arrays.
+const DEFAULT_MAX_LINE_WIDTH: usize = 2048;
+
 fn indent<W: Write>(sink: &mut W, depth: usize) -> Result<()> {
     for _ in 0..depth {
         sink.write_all(b"\t")?;
@@ -12,110 +16,34 @@ fn indent<W: Write>(sink: &mut W, depth: usize) -> Result<()> {
     Ok(())
 }
 
-fn print_property<W: Write>(
+/// Writes `"a: "` followed by a comma-separated value list, folding onto indented
+/// continuation lines (re-indented to `prop_depth`) once a line would exceed `max_line_width`.
+fn print_folded_values<W, V, I>(
     sink: &mut W,
-    property: &Property<'_>,
     prop_depth: usize,
-) -> Result<()> {
-    assert!(prop_depth > 0);
-
-    // TODO: I've never seen vector of booleans (in binary or ascii FBX)... How should it be?
-    // TODO: How will it be when other properties follows a property of array value?
-    // TODO: Implement folding of large array.
-    macro_rules! generic_vec_print {
-        ($vec:ident) => {{
-            sink.write_fmt(format_args!("*{} {{\n", $vec.len()))?;
-            indent(sink, prop_depth)?;
-            sink.write_all(b"a: ")?;
-            let mut iter = $vec.iter();
-            if let Some(&v) = iter.next() {
-                sink.write_fmt(format_args!("{}", v))?;
-            }
-            for &v in iter {
-                sink.write_fmt(format_args!(",{}", v))?;
-            }
+    max_line_width: usize,
+    values: I,
+) -> Result<()>
+where
+    W: Write,
+    V: ::std::fmt::Display,
+    I: Iterator<Item = V>,
+{
+    sink.write_all(b"a: ")?;
+    let mut col = 3; // Accounts for "a: " already written.
+    for (i, v) in values.enumerate() {
+        let piece = if i == 0 {
+            format!("{}", v)
+        } else {
+            format!(",{}", v)
+        };
+        if col > 0 && col + piece.len() > max_line_width {
             sink.write_all(b"\n")?;
-            indent(sink, prop_depth - 1)?;
-            sink.write_all(b"}")?;
-        }};
-    }
-    match *property {
-        Property::Bool(false) => {
-            sink.write_all(b"T")?;
-        }
-        Property::Bool(true) => {
-            sink.write_all(b"Y")?;
-        }
-        Property::I16(v) => {
-            sink.write_fmt(format_args!("{}", v))?;
-        }
-        Property::I32(v) => {
-            sink.write_fmt(format_args!("{}", v))?;
-        }
-        Property::I64(v) => {
-            sink.write_fmt(format_args!("{}", v))?;
-        }
-        Property::F32(v) => {
-            // NOTE: Is outputted data accurate enough?
-            sink.write_fmt(format_args!("{}", v))?;
-        }
-        Property::F64(v) => {
-            // NOTE: Is outputted data accurate enough?
-            sink.write_fmt(format_args!("{}", v))?;
-        }
-        Property::VecBool(vec) => {
-            warn!("ASCII representation of vector of boolean values may be wrong.");
-            sink.write_fmt(format_args!("*{} {{\n", vec.len()))?;
             indent(sink, prop_depth)?;
-            sink.write_all(b"a: ")?;
-            let mut iter = vec.iter();
-            if let Some(&v) = iter.next() {
-                sink.write_all(if v { b"Y" } else { b"T" })?;
-            }
-            for &v in iter {
-                sink.write_all(if v { b",Y" } else { b",T" })?;
-            }
-            sink.write_all(b"\n")?;
-            indent(sink, prop_depth - 1)?;
-            sink.write_all(b"}")?;
-        }
-        Property::VecI32(vec) => {
-            generic_vec_print!(vec);
-        }
-        Property::VecI64(vec) => {
-            generic_vec_print!(vec);
-        }
-        Property::VecF32(vec) => {
-            generic_vec_print!(vec);
-        }
-        Property::VecF64(vec) => {
-            generic_vec_print!(vec);
-        }
-        Property::String(v) => {
-            sink.write_all(b"\"")?;
-            for c in v.chars() {
-                match c {
-                    '"' => {
-                        sink.write_all(b"&quot;")?;
-                    }
-                    '\n' => {
-                        sink.write_all(b"&lf;")?;
-                    }
-                    '\r' => {
-                        sink.write_all(b"&cr;")?;
-                    }
-                    _ => {
-                        sink.write_fmt(format_args!("{}", c))?;
-                    }
-                }
-            }
-            sink.write_all(b"\"")?;
-        }
-        Property::Binary(v) => {
-            // TODO: Implement folding of long line.
-            // base64 conversion.
-            sink.write_fmt(format_args!("\"{}\"", base64::encode(v)))?;
+            col = 0;
         }
+        sink.write_all(piece.as_bytes())?;
+        col += piece.len();
     }
     Ok(())
 }
@@ -124,6 +52,9 @@ fn print_property<W: Write>(
 #[derive(Debug, Clone)]
 pub struct AsciiEmitter {
     prop_child_existence: Vec<(bool, bool)>,
+    /// Column width at which large arrays and long `Binary` base64 blobs are folded onto
+    /// continuation lines.
+    max_line_width: usize,
 }
 
 impl AsciiEmitter {
@@ -131,13 +62,20 @@ impl AsciiEmitter {
     pub fn new() -> Self {
         AsciiEmitter {
             prop_child_existence: vec![],
+            max_line_width: DEFAULT_MAX_LINE_WIDTH,
         }
     }
 
+    /// Sets the column width at which large arrays and long `Binary` base64 blobs are folded.
+    pub fn with_max_line_width(mut self, max_line_width: usize) -> Self {
+        self.max_line_width = max_line_width;
+        self
+    }
+
     pub fn emit_start_fbx<W: Write>(&mut self, sink: &mut W, ver: u32) -> Result<()> {
         if (ver < 7000) || (ver >= 8000) {
             error!("Unsupported version: {}", ver);
-            return Err(Error::UnsupportedFbxVersion(ver));
+            return Err(Error::new(0, ErrorKind::UnsupportedFbxVersion(ver)));
         }
         {
             let (major, minor) = (ver / 1000, ver % 1000);
@@ -178,11 +116,11 @@ impl AsciiEmitter {
         let prop_depth = self.prop_child_existence.len();
         let mut prop_iter = properties.iter();
         if let Some(prop) = prop_iter.next() {
-            print_property(sink, prop, prop_depth)?;
+            self.print_property(sink, prop, prop_depth)?;
         }
         for prop in prop_iter {
             sink.write_all(b", ")?;
-            print_property(sink, prop, prop_depth)?;
+            self.print_property(sink, prop, prop_depth)?;
         }
 
         Ok(())
@@ -212,4 +150,188 @@ impl AsciiEmitter {
 
         Ok(())
     }
+
+    fn print_property<W: Write>(
+        &self,
+        sink: &mut W,
+        property: &Property<'_>,
+        prop_depth: usize,
+    ) -> Result<()> {
+        assert!(prop_depth > 0);
+
+        // TODO: I've never seen vector of booleans (in binary or ascii FBX)... How should it be?
+        // TODO: How will it be when other properties follows a property of array value?
+        macro_rules! generic_vec_print {
+            ($vec:ident) => {{
+                sink.write_fmt(format_args!("*{} {{\n", $vec.len()))?;
+                indent(sink, prop_depth)?;
+                print_folded_values(sink, prop_depth, self.max_line_width, $vec.iter())?;
+                sink.write_all(b"\n")?;
+                indent(sink, prop_depth - 1)?;
+                sink.write_all(b"}")?;
+            }};
+        }
+        match *property {
+            Property::Bool(false) => {
+                sink.write_all(b"T")?;
+            }
+            Property::Bool(true) => {
+                sink.write_all(b"Y")?;
+            }
+            Property::I16(v) => {
+                sink.write_fmt(format_args!("{}", v))?;
+            }
+            Property::I32(v) => {
+                sink.write_fmt(format_args!("{}", v))?;
+            }
+            Property::I64(v) => {
+                sink.write_fmt(format_args!("{}", v))?;
+            }
+            Property::F32(v) => {
+                // NOTE: Is outputted data accurate enough?
+                sink.write_fmt(format_args!("{}", v))?;
+            }
+            Property::F64(v) => {
+                // NOTE: Is outputted data accurate enough?
+                sink.write_fmt(format_args!("{}", v))?;
+            }
+            Property::VecBool(vec) => {
+                warn!("ASCII representation of vector of boolean values may be wrong.");
+                sink.write_fmt(format_args!("*{} {{\n", vec.len()))?;
+                indent(sink, prop_depth)?;
+                print_folded_values(
+                    sink,
+                    prop_depth,
+                    self.max_line_width,
+                    vec.iter().map(|&v| if v { "Y" } else { "T" }),
+                )?;
+                sink.write_all(b"\n")?;
+                indent(sink, prop_depth - 1)?;
+                sink.write_all(b"}")?;
+            }
+            Property::VecI32(vec) => {
+                generic_vec_print!(vec);
+            }
+            Property::VecI64(vec) => {
+                generic_vec_print!(vec);
+            }
+            Property::VecF32(vec) => {
+                generic_vec_print!(vec);
+            }
+            Property::VecF64(vec) => {
+                generic_vec_print!(vec);
+            }
+            Property::String(v) => {
+                sink.write_all(b"\"")?;
+                for c in v.chars() {
+                    match c {
+                        '"' => {
+                            sink.write_all(b"&quot;")?;
+                        }
+                        '\n' => {
+                            sink.write_all(b"&lf;")?;
+                        }
+                        '\r' => {
+                            sink.write_all(b"&cr;")?;
+                        }
+                        _ => {
+                            sink.write_fmt(format_args!("{}", c))?;
+                        }
+                    }
+                }
+                sink.write_all(b"\"")?;
+            }
+            Property::Binary(v) => {
+                let encoded = base64::encode(v);
+                let mut chunks = encoded.as_bytes().chunks(self.max_line_width).peekable();
+                sink.write_all(b"\"")?;
+                while let Some(chunk) = chunks.next() {
+                    sink.write_all(chunk)?;
+                    if chunks.peek().is_some() {
+                        sink.write_all(b"\n")?;
+                        indent(sink, prop_depth)?;
+                    }
+                }
+                sink.write_all(b"\"")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Emitter<W> for AsciiEmitter {
+    fn emit_start_fbx(&mut self, sink: &mut W, ver: u32) -> Result<()> {
+        AsciiEmitter::emit_start_fbx(self, sink, ver)
+    }
+
+    fn emit_end_fbx(&mut self, sink: &mut W) -> Result<()> {
+        AsciiEmitter::emit_end_fbx(self, sink)
+    }
+
+    fn emit_start_node(&mut self, sink: &mut W, name: &str, properties: &[Property<'_>]) -> Result<()> {
+        AsciiEmitter::emit_start_node(self, sink, name, properties)
+    }
+
+    fn emit_end_node(&mut self, sink: &mut W) -> Result<()> {
+        AsciiEmitter::emit_end_node(self, sink)
+    }
+
+    fn emit_comment(&mut self, sink: &mut W, comment: &str) -> Result<()> {
+        AsciiEmitter::emit_comment(self, sink, comment)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_array_fits_on_a_single_line() {
+        let mut sink = Vec::new();
+        let emitter = AsciiEmitter::new().with_max_line_width(2048);
+        let values: Vec<i32> = vec![1, 2, 3];
+        emitter
+            .print_property(&mut sink, &Property::VecI32(&values), 1)
+            .unwrap();
+        let text = String::from_utf8(sink).unwrap();
+        // "{\n\ta: 1,2,3\n}": one newline after the opening brace, one before the closing
+        // brace, and no *extra* fold of the value list itself.
+        assert_eq!(text.matches('\n').count(), 2);
+        assert!(text.contains("a: 1,2,3"));
+    }
+
+    #[test]
+    fn long_array_folds_onto_an_indented_continuation_line() {
+        let mut sink = Vec::new();
+        // Narrow enough that "a: 1,2,3,4,5" can't fit on one line.
+        let emitter = AsciiEmitter::new().with_max_line_width(8);
+        let values: Vec<i32> = vec![1, 2, 3, 4, 5];
+        emitter
+            .print_property(&mut sink, &Property::VecI32(&values), 1)
+            .unwrap();
+        let text = String::from_utf8(sink).unwrap();
+        // At least one fold happened beyond the two newlines around the braces.
+        assert!(text.matches('\n').count() > 2);
+        // Both the "a: " line and the continuation line start with a newline and one tab.
+        assert!(
+            text.matches("\n\t").count() >= 2,
+            "continuation lines are indented to prop_depth"
+        );
+    }
+
+    #[test]
+    fn long_base64_blob_is_chunked_at_max_line_width() {
+        let mut sink = Vec::new();
+        let emitter = AsciiEmitter::new().with_max_line_width(4);
+        let data = [0u8; 16];
+        emitter
+            .print_property(&mut sink, &Property::Binary(&data), 1)
+            .unwrap();
+        let text = String::from_utf8(sink).unwrap();
+        let inner = text.trim_matches('"');
+        for line in inner.split('\n') {
+            let line = line.trim_start_matches('\t');
+            assert!(line.len() <= 4);
+        }
+    }
 }
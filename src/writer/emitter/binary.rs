@@ -1,14 +1,83 @@
 //! Contains implementation of Binary FBX emitter.
 
-use std::io::{Write, Seek};
-use writer::error::{Result, Error};
+use std::io::{Seek, SeekFrom, Write};
+
+use byteorder::{LittleEndian, WriteBytesExt};
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use log::warn;
+
+use crate::common::Property;
+use crate::writer::emitter::Emitter;
+use crate::writer::error::{Error, ErrorKind, Result};
+
+/// Magic bytes every binary FBX file starts with.
+const MAGIC: &[u8] = b"Kaydara FBX Binary  \x00\x1a\x00";
+
+/// FBX version at and above which the `EndOffset`/`NumProperties`/`PropertyListLength` header
+/// fields widen from `u32` to `u64`.
+const VERSION_WIDE_OFFSETS: u32 = 7500;
+
+/// 16-byte footer id written right after the final top-level null record (and any alignment
+/// padding before it).
+const FOOTER_ID: [u8; 16] = [
+    0xfa, 0xbc, 0xa8, 0xc0, 0xd0, 0x0c, 0x0d, 0xd0, 0x00, 0xc0, 0xff, 0xff, 0xb1, 0x00, 0x19, 0x6f,
+];
+
+/// Zero bytes used to pad the stream up to a 16-byte-aligned offset before `FOOTER_ID`.
+const FOOTER_PADDING: [u8; 16] = [0u8; 16];
+
+/// Closing magic sequence of the footer; written twice (16 bytes total).
+const FOOTER_MAGIC_TAIL: [u8; 8] = [0xf8, 0x5a, 0x8c, 0x6a, 0xde, 0xf5, 0xd9, 0x7e];
+
+/// Runs a fallible write/seek against `$sink`, stamping any `io::Error` with the stream
+/// position captured right before the operation, so a failure partway through a large write
+/// (e.g. a vertex array) reports where it happened instead of byte offset `0`.
+macro_rules! tracked {
+    ($sink:expr, $op:expr) => {{
+        let pos = stream_pos($sink)?;
+        $op.map_err(|err| Error::new(pos, err))?
+    }};
+}
+
+/// Position tracked while a node is open, so `emit_end_node` can seek back and patch the
+/// header fields that aren't known until the node's body has been written.
+#[derive(Debug, Clone, Copy)]
+struct NodePos {
+    /// Stream position of this node's `EndOffset` field.
+    header_pos: u64,
+    /// Whether a child node has been emitted under this node so far.
+    has_child: bool,
+}
+
+/// Controls when array properties (`VecI32`, `VecF32`, ...) are DEFLATE/zlib-compressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArrayCompression {
+    /// Always emit raw little-endian elements.
+    Never,
+    /// Always zlib-compress the element payload.
+    Always,
+    /// Compress only once the raw element payload reaches `threshold_bytes`.
+    Auto {
+        /// Minimum raw payload size, in bytes, before compression kicks in.
+        threshold_bytes: usize,
+    },
+}
+
+impl Default for ArrayCompression {
+    fn default() -> Self {
+        ArrayCompression::Auto {
+            threshold_bytes: 128,
+        }
+    }
+}
 
 /// A writer for Binary FBX.
 #[derive(Debug, Clone)]
 pub struct BinaryEmitter {
     version: u32,
-    pos: u64,
-    end_offset_pos_stack: Vec<u64>,
+    end_offset_pos_stack: Vec<NodePos>,
+    compression: ArrayCompression,
 }
 
 impl BinaryEmitter {
@@ -16,12 +85,440 @@ impl BinaryEmitter {
     pub fn new(version: u32) -> Self {
         BinaryEmitter {
             version: version,
-            pos: 0,
             end_offset_pos_stack: vec![],
+            compression: ArrayCompression::default(),
+        }
+    }
+
+    /// Sets the policy for compressing array properties.
+    pub fn with_array_compression(mut self, compression: ArrayCompression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Returns whether header/offset fields are 64-bit for the target version.
+    fn wide_offsets(&self) -> bool {
+        self.version >= VERSION_WIDE_OFFSETS
+    }
+
+    /// Size in bytes of a single `EndOffset`/`NumProperties`/`PropertyListLength` field.
+    fn offset_width(&self) -> u64 {
+        if self.wide_offsets() {
+            8
+        } else {
+            4
+        }
+    }
+
+    fn write_offset<W: Write + Seek>(&self, sink: &mut W, value: u64) -> Result<()> {
+        if self.wide_offsets() {
+            tracked!(sink, sink.write_u64::<LittleEndian>(value));
+        } else {
+            tracked!(sink, sink.write_u32::<LittleEndian>(value as u32));
         }
+        Ok(())
+    }
+
+    /// Writes a null record: three zeroed offset fields plus a zero name length byte.
+    /// Terminates a node's child list, or (at top level) the whole node tree.
+    fn write_null_record<W: Write + Seek>(&self, sink: &mut W) -> Result<()> {
+        self.write_offset(sink, 0)?;
+        self.write_offset(sink, 0)?;
+        self.write_offset(sink, 0)?;
+        tracked!(sink, sink.write_u8(0));
+        Ok(())
+    }
+
+    fn write_footer<W: Write + Seek>(&self, sink: &mut W) -> Result<()> {
+        // Pad up to the next 16-byte-aligned offset before the footer id.
+        let pos = stream_pos(sink)?;
+        let pad = ((16 - (pos % 16)) % 16) as usize;
+        if pad > 0 {
+            tracked!(sink, sink.write_all(&FOOTER_PADDING[..pad]));
+        }
+
+        tracked!(sink, sink.write_all(&FOOTER_ID));
+        tracked!(sink, sink.write_all(&[0u8; 4]));
+        tracked!(sink, sink.write_u32::<LittleEndian>(self.version));
+        tracked!(sink, sink.write_all(&[0u8; 120]));
+        // The closing magic tail is the 8-byte sequence written twice, filling 16 bytes.
+        tracked!(sink, sink.write_all(&FOOTER_MAGIC_TAIL));
+        tracked!(sink, sink.write_all(&FOOTER_MAGIC_TAIL));
+        Ok(())
+    }
+
+    fn write_property<W: Write + Seek>(&self, sink: &mut W, property: &Property<'_>) -> Result<()> {
+        match *property {
+            Property::Bool(v) => {
+                tracked!(sink, sink.write_all(b"C"));
+                tracked!(sink, sink.write_u8(v as u8));
+            }
+            Property::I16(v) => {
+                tracked!(sink, sink.write_all(b"Y"));
+                tracked!(sink, sink.write_i16::<LittleEndian>(v));
+            }
+            Property::I32(v) => {
+                tracked!(sink, sink.write_all(b"I"));
+                tracked!(sink, sink.write_i32::<LittleEndian>(v));
+            }
+            Property::I64(v) => {
+                tracked!(sink, sink.write_all(b"L"));
+                tracked!(sink, sink.write_i64::<LittleEndian>(v));
+            }
+            Property::F32(v) => {
+                tracked!(sink, sink.write_all(b"F"));
+                tracked!(sink, sink.write_f32::<LittleEndian>(v));
+            }
+            Property::F64(v) => {
+                tracked!(sink, sink.write_all(b"D"));
+                tracked!(sink, sink.write_f64::<LittleEndian>(v));
+            }
+            Property::String(v) => {
+                tracked!(sink, sink.write_all(b"S"));
+                tracked!(sink, sink.write_u32::<LittleEndian>(v.len() as u32));
+                tracked!(sink, sink.write_all(v.as_bytes()));
+            }
+            Property::Binary(v) => {
+                tracked!(sink, sink.write_all(b"R"));
+                tracked!(sink, sink.write_u32::<LittleEndian>(v.len() as u32));
+                tracked!(sink, sink.write_all(v));
+            }
+            Property::VecBool(v) => {
+                let mut raw = Vec::with_capacity(v.len());
+                for &e in v {
+                    raw.write_u8(e as u8)?;
+                }
+                self.write_array(sink, b'b', v.len() as u32, &raw)?;
+            }
+            Property::VecI32(v) => {
+                let mut raw = Vec::with_capacity(v.len() * 4);
+                for &e in v {
+                    raw.write_i32::<LittleEndian>(e)?;
+                }
+                self.write_array(sink, b'i', v.len() as u32, &raw)?;
+            }
+            Property::VecI64(v) => {
+                let mut raw = Vec::with_capacity(v.len() * 8);
+                for &e in v {
+                    raw.write_i64::<LittleEndian>(e)?;
+                }
+                self.write_array(sink, b'l', v.len() as u32, &raw)?;
+            }
+            Property::VecF32(v) => {
+                let mut raw = Vec::with_capacity(v.len() * 4);
+                for &e in v {
+                    raw.write_f32::<LittleEndian>(e)?;
+                }
+                self.write_array(sink, b'f', v.len() as u32, &raw)?;
+            }
+            Property::VecF64(v) => {
+                let mut raw = Vec::with_capacity(v.len() * 8);
+                for &e in v {
+                    raw.write_f64::<LittleEndian>(e)?;
+                }
+                self.write_array(sink, b'd', v.len() as u32, &raw)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes an array property's `ArrayLength`/`Encoding`/`CompressedLength` header and
+    /// payload, compressing `raw` first when `self.compression` calls for it. `CompressedLength`
+    /// is filled in from the (possibly compressed) payload, which is always known by the time
+    /// we write it since compression happens into an in-memory buffer first.
+    fn write_array<W: Write + Seek>(&self, sink: &mut W, tag: u8, len: u32, raw: &[u8]) -> Result<()> {
+        tracked!(sink, sink.write_all(&[tag]));
+        tracked!(sink, sink.write_u32::<LittleEndian>(len));
+
+        let should_compress = match self.compression {
+            ArrayCompression::Never => false,
+            ArrayCompression::Always => true,
+            ArrayCompression::Auto { threshold_bytes } => raw.len() >= threshold_bytes,
+        };
+
+        if should_compress {
+            // Compressing into an in-memory buffer first; these can't fail with a stream
+            // position worth reporting, so they're left as plain `?` conversions.
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(raw)?;
+            let compressed = encoder.finish()?;
+            tracked!(sink, sink.write_u32::<LittleEndian>(1));
+            tracked!(sink, sink.write_u32::<LittleEndian>(compressed.len() as u32));
+            tracked!(sink, sink.write_all(&compressed));
+        } else {
+            tracked!(sink, sink.write_u32::<LittleEndian>(0));
+            tracked!(sink, sink.write_u32::<LittleEndian>(raw.len() as u32));
+            tracked!(sink, sink.write_all(raw));
+        }
+
+        Ok(())
     }
 
     pub fn emit_start_fbx<W: Write + Seek>(&mut self, sink: &mut W, ver: u32) -> Result<()> {
-        Err(Error::Unimplemented("BinaryEmitter::emit_start_fbx() is unimplemented yet".to_string()))
+        if (ver < 7000) || (ver >= 8000) {
+            return Err(Error::new(0, ErrorKind::UnsupportedFbxVersion(ver)));
+        }
+        self.version = ver;
+        tracked!(sink, sink.write_all(MAGIC));
+        tracked!(sink, sink.write_u32::<LittleEndian>(ver));
+
+        Ok(())
+    }
+
+    pub fn emit_end_fbx<W: Write + Seek>(&mut self, sink: &mut W) -> Result<()> {
+        // Null record terminating the top-level node list, then the version footer block.
+        self.write_null_record(sink)?;
+        self.write_footer(sink)?;
+
+        Ok(())
+    }
+
+    pub fn emit_start_node<W: Write + Seek>(
+        &mut self,
+        sink: &mut W,
+        name: &str,
+        properties: &[Property<'_>],
+    ) -> Result<()> {
+        let name_bytes = name.as_bytes();
+        if name_bytes.len() > u8::MAX as usize {
+            let pos = stream_pos(sink)?;
+            return Err(Error::new(
+                pos,
+                ErrorKind::DataError(format!(
+                    "node name too long for binary FBX: {} bytes (max {})",
+                    name_bytes.len(),
+                    u8::MAX
+                )),
+            ));
+        }
+
+        if let Some(parent) = self.end_offset_pos_stack.last_mut() {
+            parent.has_child = true;
+        }
+
+        let header_pos = stream_pos(sink)?;
+        // Placeholders for `EndOffset` and `PropertyListLength`; patched below/on `emit_end_node`.
+        self.write_offset(sink, 0)?;
+        self.write_offset(sink, properties.len() as u64)?;
+        self.write_offset(sink, 0)?;
+
+        tracked!(sink, sink.write_u8(name_bytes.len() as u8));
+        tracked!(sink, sink.write_all(name_bytes));
+
+        let prop_list_start = stream_pos(sink)?;
+        for property in properties {
+            self.write_property(sink, property)?;
+        }
+        let prop_list_end = stream_pos(sink)?;
+
+        tracked!(sink, sink.seek(SeekFrom::Start(header_pos + 2 * self.offset_width())));
+        self.write_offset(sink, prop_list_end - prop_list_start)?;
+        tracked!(sink, sink.seek(SeekFrom::Start(prop_list_end)));
+
+        self.end_offset_pos_stack.push(NodePos {
+            header_pos,
+            has_child: false,
+        });
+
+        Ok(())
+    }
+
+    pub fn emit_end_node<W: Write + Seek>(&mut self, sink: &mut W) -> Result<()> {
+        let NodePos {
+            header_pos,
+            has_child,
+        } = match self.end_offset_pos_stack.pop() {
+            Some(node_pos) => node_pos,
+            None => {
+                let pos = stream_pos(sink)?;
+                return Err(Error::new(
+                    pos,
+                    ErrorKind::DataError(
+                        "emit_end_node() called without matching emit_start_node()".to_string(),
+                    ),
+                ));
+            }
+        };
+
+        if has_child {
+            self.write_null_record(sink)?;
+        }
+
+        let end_pos = stream_pos(sink)?;
+        tracked!(sink, sink.seek(SeekFrom::Start(header_pos)));
+        self.write_offset(sink, end_pos)?;
+        tracked!(sink, sink.seek(SeekFrom::Start(end_pos)));
+
+        Ok(())
+    }
+}
+
+fn stream_pos<W: Seek>(sink: &mut W) -> Result<u64> {
+    Ok(sink.seek(SeekFrom::Current(0))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryInto;
+    use std::io::Cursor;
+
+    fn read_u32(buf: &[u8], pos: usize) -> u32 {
+        u32::from_le_bytes(buf[pos..pos + 4].try_into().unwrap())
+    }
+
+    fn read_u64(buf: &[u8], pos: usize) -> u64 {
+        u64::from_le_bytes(buf[pos..pos + 8].try_into().unwrap())
+    }
+
+    #[test]
+    fn scalar_property_is_encoded_with_type_code_and_little_endian_value() {
+        let mut sink = Cursor::new(Vec::new());
+        let emitter = BinaryEmitter::new(7400);
+        emitter
+            .write_property(&mut sink, &Property::I32(-7))
+            .unwrap();
+        let buf = sink.into_inner();
+        assert_eq!(buf[0], b'I');
+        assert_eq!(read_u32(&buf, 1) as i32, -7);
+        assert_eq!(buf.len(), 5);
+    }
+
+    #[test]
+    fn narrow_offsets_are_four_bytes_below_version_7500() {
+        let mut sink = Cursor::new(Vec::new());
+        let mut emitter = BinaryEmitter::new(7400);
+        emitter.emit_start_node(&mut sink, "A", &[]).unwrap();
+        emitter.emit_end_node(&mut sink).unwrap();
+        let buf = sink.into_inner();
+        // header_pos(0) EndOffset/NumProperties/PropertyListLength are 4 bytes each here.
+        assert_eq!(read_u32(&buf, 0) as u64, buf.len() as u64 - 0);
+        assert_eq!(read_u32(&buf, 4), 0);
+        assert_eq!(read_u32(&buf, 8), 0);
+    }
+
+    #[test]
+    fn wide_offsets_are_eight_bytes_at_version_7500() {
+        let mut sink = Cursor::new(Vec::new());
+        let mut emitter = BinaryEmitter::new(7500);
+        emitter.emit_start_node(&mut sink, "A", &[]).unwrap();
+        emitter.emit_end_node(&mut sink).unwrap();
+        let buf = sink.into_inner();
+        assert_eq!(read_u64(&buf, 0), buf.len() as u64);
+        assert_eq!(read_u64(&buf, 8), 0);
+        assert_eq!(read_u64(&buf, 16), 0);
+    }
+
+    #[test]
+    fn end_offset_is_backpatched_to_cover_nested_children() {
+        let mut sink = Cursor::new(Vec::new());
+        let mut emitter = BinaryEmitter::new(7400);
+        emitter.emit_start_node(&mut sink, "Parent", &[]).unwrap();
+        emitter.emit_start_node(&mut sink, "Child", &[]).unwrap();
+        emitter.emit_end_node(&mut sink).unwrap();
+        emitter.emit_end_node(&mut sink).unwrap();
+        let buf = sink.into_inner();
+        assert_eq!(read_u32(&buf, 0) as usize, buf.len());
+    }
+
+    #[test]
+    fn oversized_node_name_is_rejected_without_corrupting_the_stream() {
+        let mut sink = Cursor::new(Vec::new());
+        let mut emitter = BinaryEmitter::new(7400);
+        let name = "x".repeat(u8::MAX as usize + 1);
+        let err = emitter
+            .emit_start_node(&mut sink, &name, &[])
+            .unwrap_err();
+        assert!(matches!(err.kind(), ErrorKind::DataError(_)));
+        assert!(sink.into_inner().is_empty());
+    }
+
+    #[test]
+    fn footer_is_padded_to_16_bytes_and_tail_is_written_twice() {
+        let mut sink = Cursor::new(Vec::new());
+        let emitter = BinaryEmitter::new(7400);
+        // One byte of unaligned content before the footer, to exercise the padding path.
+        sink.write_all(&[0u8]).unwrap();
+        emitter.write_footer(&mut sink).unwrap();
+        let buf = sink.into_inner();
+        assert_eq!(buf.len() % 16, 0);
+        let footer_start = 16; // 1 content byte + 15 bytes padding.
+        assert_eq!(&buf[footer_start..footer_start + 16], &FOOTER_ID[..]);
+        let tail_start = buf.len() - 16;
+        assert_eq!(&buf[tail_start..tail_start + 8], &FOOTER_MAGIC_TAIL[..]);
+        assert_eq!(&buf[tail_start + 8..], &FOOTER_MAGIC_TAIL[..]);
+    }
+
+    #[test]
+    fn array_compression_auto_only_compresses_past_the_threshold() {
+        let emitter = BinaryEmitter::new(7400).with_array_compression(ArrayCompression::Auto {
+            threshold_bytes: 64,
+        });
+
+        let mut sink = Cursor::new(Vec::new());
+        let values: Vec<i32> = vec![1, 2, 3];
+        emitter
+            .write_property(&mut sink, &Property::VecI32(&values))
+            .unwrap();
+        let buf = sink.into_inner();
+        // Encoding byte follows tag(1) + ArrayLength(4).
+        assert_eq!(buf[5], 0, "small array should not be compressed");
+
+        let mut sink = Cursor::new(Vec::new());
+        let large_values: Vec<i32> = (0..64).collect();
+        emitter
+            .write_property(&mut sink, &Property::VecI32(&large_values))
+            .unwrap();
+        let buf = sink.into_inner();
+        assert_eq!(buf[5], 1, "large array should be compressed");
+    }
+
+    #[test]
+    fn array_compression_never_never_compresses() {
+        let mut sink = Cursor::new(Vec::new());
+        let emitter = BinaryEmitter::new(7400).with_array_compression(ArrayCompression::Never);
+        let values: Vec<i32> = (0..64).collect();
+        emitter
+            .write_property(&mut sink, &Property::VecI32(&values))
+            .unwrap();
+        let buf = sink.into_inner();
+        assert_eq!(buf[5], 0);
+    }
+
+    #[test]
+    fn array_compression_always_always_compresses() {
+        let mut sink = Cursor::new(Vec::new());
+        let emitter = BinaryEmitter::new(7400).with_array_compression(ArrayCompression::Always);
+        let values: Vec<i32> = vec![1, 2, 3];
+        emitter
+            .write_property(&mut sink, &Property::VecI32(&values))
+            .unwrap();
+        let buf = sink.into_inner();
+        assert_eq!(buf[5], 1);
+    }
+}
+
+impl<W: Write + Seek> Emitter<W> for BinaryEmitter {
+    fn emit_start_fbx(&mut self, sink: &mut W, ver: u32) -> Result<()> {
+        BinaryEmitter::emit_start_fbx(self, sink, ver)
+    }
+
+    fn emit_end_fbx(&mut self, sink: &mut W) -> Result<()> {
+        BinaryEmitter::emit_end_fbx(self, sink)
+    }
+
+    fn emit_start_node(&mut self, sink: &mut W, name: &str, properties: &[Property<'_>]) -> Result<()> {
+        BinaryEmitter::emit_start_node(self, sink, name, properties)
+    }
+
+    fn emit_end_node(&mut self, sink: &mut W) -> Result<()> {
+        BinaryEmitter::emit_end_node(self, sink)
+    }
+
+    /// Binary FBX has no comment syntax, so comments are simply dropped.
+    fn emit_comment(&mut self, _sink: &mut W, comment: &str) -> Result<()> {
+        warn!("Binary FBX has no comment syntax; dropping comment: {:?}", comment);
+        Ok(())
     }
 }
@@ -0,0 +1,31 @@
+//! Contains FBX event emitter implementations.
+
+use std::io::Write;
+
+use crate::common::Property;
+use crate::writer::error::Result;
+
+pub mod ascii;
+pub mod binary;
+
+pub use self::ascii::AsciiEmitter;
+pub use self::binary::BinaryEmitter;
+
+/// Common event surface implemented by every FBX emitter backend (ASCII, binary, ...).
+///
+/// `EventWriter` drives a single stream of these events against whichever backend was
+/// selected at construction, so callers don't need to hardcode a format. Only `Write` is
+/// required here; backends that need to patch earlier bytes (e.g. `BinaryEmitter`) add
+/// their own `Seek` bound on their `impl Emitter<W>`.
+pub trait Emitter<W: Write> {
+    /// Emits the very first event, declaring the target FBX version.
+    fn emit_start_fbx(&mut self, sink: &mut W, ver: u32) -> Result<()>;
+    /// Emits the final event, closing out the document.
+    fn emit_end_fbx(&mut self, sink: &mut W) -> Result<()>;
+    /// Emits the start of a node, with all of its properties.
+    fn emit_start_node(&mut self, sink: &mut W, name: &str, properties: &[Property<'_>]) -> Result<()>;
+    /// Emits the end of the most recently started node.
+    fn emit_end_node(&mut self, sink: &mut W) -> Result<()>;
+    /// Emits a comment. Backends with no comment syntax (e.g. binary) drop it.
+    fn emit_comment(&mut self, sink: &mut W, comment: &str) -> Result<()>;
+}
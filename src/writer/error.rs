@@ -0,0 +1,164 @@
+extern crate byteorder;
+
+use std::error;
+use std::fmt;
+use std::io;
+use std::string;
+
+pub type Result<T> = ::std::result::Result<T, Error>;
+
+#[derive(Debug)]
+pub struct Error {
+    pos: u64,
+    kind: ErrorKind,
+}
+
+impl Error {
+    pub fn new<K: Into<ErrorKind>>(pos: u64, kind: K) -> Self {
+        Error {
+            pos: pos,
+            kind: kind.into(),
+        }
+    }
+
+    /// Returns the byte offset in the stream at which the error occurred.
+    ///
+    /// This is `0` for errors raised before anything has been written to the stream (e.g. an
+    /// unsupported FBX version, checked before any bytes are emitted). Emitters that can seek
+    /// their sink, such as `BinaryEmitter`, capture the real position with `Error::new` before
+    /// every fallible write instead of relying on the blanket `io::Error` conversion below.
+    pub fn position(&self) -> u64 {
+        self.pos
+    }
+
+    /// Returns the kind of error that occurred.
+    pub fn kind(&self) -> &ErrorKind {
+        &self.kind
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (at byte offset {})", self.kind, self.pos)
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self.kind {
+            ErrorKind::FromUtf8Error(ref err) => Some(err),
+            ErrorKind::Io(ref err) => Some(err),
+            ErrorKind::InvalidMagic
+            | ErrorKind::DataError(..)
+            | ErrorKind::UnexpectedEof
+            | ErrorKind::UnsupportedFbxVersion(..)
+            | ErrorKind::Unimplemented(..) => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ErrorKind {
+    FromUtf8Error(string::FromUtf8Error),
+    InvalidMagic,
+    Io(io::Error),
+    DataError(String),
+    UnexpectedEof,
+    UnsupportedFbxVersion(u32),
+    Unimplemented(String),
+}
+
+impl From<string::FromUtf8Error> for ErrorKind {
+    fn from(err: string::FromUtf8Error) -> ErrorKind {
+        ErrorKind::FromUtf8Error(err)
+    }
+}
+
+impl From<io::Error> for ErrorKind {
+    fn from(err: io::Error) -> ErrorKind {
+        ErrorKind::Io(err)
+    }
+}
+
+// NOTE: The real stream position isn't known at the call site of `?`, so these blanket
+// conversions stamp `pos = 0`. `BinaryEmitter` avoids relying on this for its sink I/O: it
+// captures the actual stream position and builds the `Error` with `Error::new` directly
+// (see its `tracked!` macro) before every fallible write or seek. A failure of the position
+// query itself (`stream_pos`) still goes through this conversion and reports `0`.
+impl From<string::FromUtf8Error> for Error {
+    fn from(err: string::FromUtf8Error) -> Error {
+        Error::new(0, err)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        Error::new(0, err)
+    }
+}
+
+impl From<byteorder::Error> for ErrorKind {
+    fn from(err: byteorder::Error) -> ErrorKind {
+        match err {
+            byteorder::Error::UnexpectedEOF => ErrorKind::UnexpectedEof,
+            byteorder::Error::Io(err) => ErrorKind::Io(err),
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            ErrorKind::FromUtf8Error(ref err) => write!(f, "invalid UTF-8: {}", err),
+            ErrorKind::InvalidMagic => write!(f, "invalid FBX magic bytes"),
+            ErrorKind::Io(ref err) => write!(f, "I/O error: {}", err),
+            ErrorKind::DataError(ref msg) => write!(f, "data error: {}", msg),
+            ErrorKind::UnexpectedEof => write!(f, "unexpected end of file"),
+            ErrorKind::UnsupportedFbxVersion(ver) => write!(f, "unsupported FBX version: {}", ver),
+            ErrorKind::Unimplemented(ref msg) => write!(f, "not implemented: {}", msg),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_reports_the_kind_and_byte_offset() {
+        let err = Error::new(42, ErrorKind::InvalidMagic);
+        assert_eq!(
+            err.to_string(),
+            "invalid FBX magic bytes (at byte offset 42)"
+        );
+    }
+
+    #[test]
+    fn position_and_kind_accessors_return_what_was_constructed() {
+        let err = Error::new(7, ErrorKind::UnexpectedEof);
+        assert_eq!(err.position(), 7);
+        assert!(matches!(err.kind(), ErrorKind::UnexpectedEof));
+    }
+
+    #[test]
+    fn source_returns_the_wrapped_io_error() {
+        let io_err = io::Error::new(io::ErrorKind::Other, "disk full");
+        let err = Error::new(3, ErrorKind::Io(io_err));
+        let source = error::Error::source(&err).expect("io error should have a source");
+        assert_eq!(source.to_string(), "disk full");
+    }
+
+    #[test]
+    fn data_error_has_no_source() {
+        let err = Error::new(0, ErrorKind::DataError("bad data".to_string()));
+        assert!(error::Error::source(&err).is_none());
+    }
+
+    #[test]
+    fn from_io_error_stamps_position_zero() {
+        let io_err = io::Error::new(io::ErrorKind::Other, "boom");
+        let err: Error = io_err.into();
+        assert_eq!(err.position(), 0);
+        assert!(matches!(err.kind(), ErrorKind::Io(_)));
+    }
+}
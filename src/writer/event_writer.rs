@@ -0,0 +1,115 @@
+//! Contains `EventWriter`, a single event stream that dispatches to a chosen emitter backend.
+
+use std::io::{Seek, Write};
+
+use crate::common::Property;
+use crate::writer::emitter::{AsciiEmitter, BinaryEmitter, Emitter};
+use crate::writer::error::Result;
+
+/// Drives a single FBX event stream against whichever emitter backend was selected at
+/// construction, so callers can write ASCII or binary FBX through one API.
+pub struct EventWriter<W> {
+    sink: W,
+    emitter: Box<dyn Emitter<W>>,
+}
+
+impl<W: Write> EventWriter<W> {
+    /// Creates a writer that emits ASCII FBX into `sink`.
+    pub fn new_ascii(sink: W) -> Self {
+        EventWriter {
+            sink: sink,
+            emitter: Box::new(AsciiEmitter::new()),
+        }
+    }
+
+    /// Emits the very first event, declaring the target FBX version.
+    pub fn emit_start_fbx(&mut self, ver: u32) -> Result<()> {
+        self.emitter.emit_start_fbx(&mut self.sink, ver)
+    }
+
+    /// Emits the final event, closing out the document.
+    pub fn emit_end_fbx(&mut self) -> Result<()> {
+        self.emitter.emit_end_fbx(&mut self.sink)
+    }
+
+    /// Emits the start of a node, with all of its properties.
+    pub fn emit_start_node(&mut self, name: &str, properties: &[Property<'_>]) -> Result<()> {
+        self.emitter.emit_start_node(&mut self.sink, name, properties)
+    }
+
+    /// Emits the end of the most recently started node.
+    pub fn emit_end_node(&mut self) -> Result<()> {
+        self.emitter.emit_end_node(&mut self.sink)
+    }
+
+    /// Emits a comment. Backends with no comment syntax (e.g. binary) drop it.
+    pub fn emit_comment(&mut self, comment: &str) -> Result<()> {
+        self.emitter.emit_comment(&mut self.sink, comment)
+    }
+
+    /// Consumes the writer, returning the underlying sink.
+    pub fn into_inner(self) -> W {
+        self.sink
+    }
+}
+
+impl<W: Write + Seek> EventWriter<W> {
+    /// Creates a writer that emits Binary FBX into `sink`, targeting FBX version `version`.
+    /// The version later passed to `emit_start_fbx` replaces it.
+    ///
+    /// Binary FBX needs to patch node-length fields after the fact, so this constructor
+    /// (unlike `new_ascii`) requires a seekable sink.
+    pub fn new_binary(sink: W, version: u32) -> Self {
+        EventWriter {
+            sink: sink,
+            emitter: Box::new(BinaryEmitter::new(version)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn new_ascii_round_trips_a_node_through_a_non_seekable_sink() {
+        let mut writer = EventWriter::new_ascii(Vec::new());
+        writer.emit_start_fbx(7400).unwrap();
+        writer
+            .emit_start_node("Node", &[Property::I32(42)])
+            .unwrap();
+        writer.emit_end_node().unwrap();
+        writer.emit_end_fbx().unwrap();
+        let text = String::from_utf8(writer.into_inner()).unwrap();
+        assert!(text.starts_with("; FBX 7.4.0 project file\n"));
+        assert!(text.contains("Node: 42"));
+    }
+
+    #[test]
+    fn new_binary_round_trips_a_node_through_a_seekable_sink() {
+        let mut writer = EventWriter::new_binary(Cursor::new(Vec::new()), 7400);
+        writer.emit_start_fbx(7400).unwrap();
+        writer
+            .emit_start_node("Node", &[Property::I32(42)])
+            .unwrap();
+        writer.emit_end_node().unwrap();
+        writer.emit_end_fbx().unwrap();
+        let buf = writer.into_inner().into_inner();
+        assert_eq!(&buf[0..21], b"Kaydara FBX Binary  \x00");
+        assert!(buf.len() > 27);
+    }
+
+    #[test]
+    fn binary_emit_comment_does_not_touch_the_sink() {
+        let mut writer = EventWriter::new_binary(Cursor::new(Vec::new()), 7400);
+        writer.emit_start_fbx(7400).unwrap();
+        let before = writer.into_inner().into_inner();
+
+        let mut writer = EventWriter::new_binary(Cursor::new(before.clone()), 7400);
+        writer.emit_comment("this should be dropped").unwrap();
+        let after = writer.into_inner().into_inner();
+
+        assert_eq!(before, after);
+    }
+}
@@ -0,0 +1,8 @@
+//! Contains writer (event emitter) implementations.
+
+pub mod emitter;
+pub mod error;
+pub mod event_writer;
+
+pub use self::emitter::Emitter;
+pub use self::event_writer::EventWriter;